@@ -31,7 +31,7 @@
 //! error!("This is an error message");
 //! ```
 
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 #![cfg_attr(target_arch = "riscv32", feature(impl_trait_in_assoc_type))]
 
 // Re-export defmt for users
@@ -40,6 +40,11 @@ pub use defmt;
 // Re-export defmt logging macros directly
 pub use defmt::{debug, error, info, trace, warn};
 
+// Lock-free SPSC ring buffer shared by the logger implementations below to
+// decouple frame production (in the defmt critical section) from console
+// I/O (on the `drain_task` embassy task).
+mod ring;
+
 // Conditionally include logger implementation based on features
 #[cfg(feature = "logger_b")]
 mod logger_b;
@@ -47,16 +52,36 @@ mod logger_b;
 #[cfg(not(feature = "logger_b"))]
 mod logger_a;
 
+#[cfg(feature = "logger_b")]
+pub(crate) use logger_b::drain;
+
+#[cfg(not(feature = "logger_b"))]
+pub(crate) use logger_a::drain;
+
 // Embassy task module (only available on riscv32)
 #[cfg(target_arch = "riscv32")]
 pub mod task;
 
 /// Timestamp function required by defmt
 ///
-/// Returns a simple counter-based timestamp. In a real implementation,
-/// this would return a hardware timer value.
+/// On `riscv32` this reads embassy-time's monotonic clock, which is backed
+/// directly by the Tock alarm counter register. That's a plain memory read
+/// with no syscall involved, so it's safe to call from inside the logger's
+/// critical section without risking a yield. Host builds have no running
+/// executor to drive the clock, so they report a constant `0`.
+///
+/// Enable the `timestamp-uptime` feature to have host-side defmt tooling
+/// render the value as a human-readable uptime (e.g. `12.345000s`) instead
+/// of a raw microsecond count.
+#[cfg(all(target_arch = "riscv32", feature = "timestamp-uptime"))]
+defmt::timestamp!("{=u64:us}", {
+    embassy_time::Instant::now().as_micros()
+});
+
+#[cfg(all(target_arch = "riscv32", not(feature = "timestamp-uptime")))]
 defmt::timestamp!("{=u64}", {
-    // For this minimal implementation, we just return 0
-    // In a real system, this would return a monotonic timestamp
-    0
+    embassy_time::Instant::now().as_micros()
 });
+
+#[cfg(not(target_arch = "riscv32"))]
+defmt::timestamp!("{=u64}", { 0 });
@@ -8,9 +8,68 @@ use core::{
 
 use libtock_console::Console;
 
+use crate::ring::RingBuffer;
+
 #[defmt::global_logger]
 struct LoggerA;
 
+/// Backing store for encoded defmt bytes. [`FrameBuf`] assembles one frame
+/// at a time from `write_bytes`, and that whole frame is pushed into this
+/// ring in a single [`RingBuffer::push`] call; [`drain`] empties it on the
+/// `drain_task` embassy task.
+static RING: RingBuffer<1024> = RingBuffer::new();
+
+/// Largest encoded frame `FrameBuf` can assemble. A frame bigger than this
+/// is dropped in its entirety rather than forwarded truncated.
+const MAX_FRAME_LEN: usize = 256;
+
+/// Accumulates one in-flight frame's bytes across the
+/// `start_frame`/`write`/`end_frame` calls that all happen within a single
+/// critical section, so the whole frame can be committed to [`RING`] as one
+/// atomic push instead of several independent ones - each of which could
+/// otherwise observe the ring as full at a different point mid-frame and
+/// leave a truncated frame behind.
+struct FrameBuf {
+    buf: [u8; MAX_FRAME_LEN],
+    len: usize,
+    /// Set once `buf` can't hold any more of the current frame; the frame
+    /// is dropped in full on [`commit`](Self::commit) instead of being
+    /// forwarded truncated.
+    overflowed: bool,
+}
+
+impl FrameBuf {
+    const fn new() -> Self {
+        FrameBuf {
+            buf: [0; MAX_FRAME_LEN],
+            len: 0,
+            overflowed: false,
+        }
+    }
+
+    /// Append `bytes` from one `write_bytes` call to the in-flight frame.
+    fn push(&mut self, bytes: &[u8]) {
+        if self.len + bytes.len() > self.buf.len() {
+            self.overflowed = true;
+            return;
+        }
+        self.buf[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+    }
+
+    /// Push the completed frame into `ring` as a single chunk, then reset
+    /// for the next frame.
+    fn commit<const N: usize>(&mut self, ring: &RingBuffer<N>) {
+        if self.overflowed {
+            ring.note_dropped();
+        } else if self.len > 0 {
+            ring.push(&self.buf[..self.len]);
+        }
+        self.len = 0;
+        self.overflowed = false;
+    }
+}
+
 static ENCODER: Encoder = Encoder::new();
 
 struct Encoder {
@@ -23,6 +82,9 @@ struct Encoder {
     cs_restore: UnsafeCell<critical_section::RestoreState>,
     /// A defmt::Encoder for encoding frames
     encoder: UnsafeCell<defmt::Encoder>,
+    /// Assembles the frame currently being encoded before it's committed to
+    /// [`RING`] as a whole; see [`FrameBuf`].
+    frame_buf: UnsafeCell<FrameBuf>,
 }
 
 impl Encoder {
@@ -32,6 +94,7 @@ impl Encoder {
             taken: AtomicBool::new(false),
             cs_restore: UnsafeCell::new(critical_section::RestoreState::invalid()),
             encoder: UnsafeCell::new(defmt::Encoder::new()),
+            frame_buf: UnsafeCell::new(FrameBuf::new()),
         }
     }
 
@@ -69,6 +132,7 @@ impl Encoder {
         unsafe {
             let encoder: &mut defmt::Encoder = &mut *self.encoder.get();
             encoder.end_frame(write_bytes);
+            (&mut *self.frame_buf.get()).commit(&RING);
             let restore = self.cs_restore.get().read();
             self.taken.store(false, Ordering::Relaxed);
             // paired with exactly one acquire call
@@ -91,24 +155,84 @@ impl Encoder {
     }
 }
 
-/// Write encoded bytes to the console output - Logger A format
+/// Append encoded bytes to the in-flight frame - Logger A format
 ///
-/// This writes the defmt-encoded bytes to the Tock console, prefixed with [DEFMT-A: marker
+/// This runs inside the defmt critical section, so it must stay cheap: it
+/// only memcpy's into `ENCODER`'s [`FrameBuf`] and never touches the console
+/// directly. The whole frame is committed to [`RING`] at once in
+/// `Encoder::release`, and the actual console I/O happens later, outside
+/// the critical section, in [`drain`].
 fn write_bytes(bytes: &[u8]) {
+    // safety: only called from within `Encoder::acquire`/`write`/`release`,
+    // which hold the defmt critical section and so have exclusive access to
+    // `frame_buf`.
+    unsafe {
+        (&mut *ENCODER.frame_buf.get()).push(bytes);
+    }
+}
+
+/// Drain the next contiguous run of bytes buffered in [`RING`] to the Tock
+/// console as one batched write (formatted by [`write_frame`]), instead of
+/// one syscall per byte. Bytes that wrap past the end of the ring are left
+/// for the next call.
+///
+/// Must be called from outside the defmt critical section (the embassy
+/// `drain_task` in `task.rs` is the only caller) since it performs console
+/// I/O that can block on slow output.
+pub(crate) fn drain() {
+    RING.drain(|bytes| {
+        write_console(bytes);
+        bytes.len()
+    });
+
+    let dropped = RING.take_dropped();
+    if dropped > 0 {
+        defmt::warn!(
+            "dropped {=usize} encoded log frame(s): ring buffer full or frame too large",
+            dropped
+        );
+    }
+}
+
+/// Write a batch of already-encoded bytes to the console - Logger A format.
+fn write_console(bytes: &[u8]) {
     #[cfg(target_arch = "riscv32")]
     {
         let mut console = Console::<libtock_runtime::TockSyscalls>::writer();
-        // Logger A uses [DEFMT-A: prefix
-        let _ = write!(console, "[DEFMT-A:");
-        for byte in bytes {
-            let _ = write!(console, "{:02X}", byte);
-        }
-        let _ = writeln!(console, "]");
+        write_frame(&mut console, bytes);
     }
 
     #[cfg(not(target_arch = "riscv32"))]
     {
         let mut console = Console::<libtock_unittest::fake::Syscalls>::writer();
+        write_frame(&mut console, bytes);
+    }
+}
+
+/// Format one batch of encoded defmt bytes onto `console`.
+///
+/// With the `rzcobs` feature, `defmt` itself is expected to be built with
+/// its `encoding-rzcobs` feature, so `bytes` already is rzCOBS-encoded,
+/// `0x00`-terminated frame data (a batch may hold several frames or part of
+/// one) - there's nothing left to add, it's forwarded as-is. Otherwise frames are written as ASCII hex wrapped in the
+/// `[DEFMT-A:...]` marker `emulator-run` looks for; this is double the size
+/// on the wire but stays human-readable, which is handy for debugging.
+fn write_frame(console: &mut impl Write, bytes: &[u8]) {
+    #[cfg(feature = "rzcobs")]
+    {
+        for chunk in bytes.chunks(32) {
+            // safety: this `Write` impl only forwards the bytes of the `&str`
+            // to the Tock console syscall; it never inspects them for UTF-8
+            // validity, so reusing it to push an arbitrary byte run through
+            // is sound for this console even though rzCOBS output isn't
+            // valid UTF-8 in general.
+            let s = unsafe { core::str::from_utf8_unchecked(chunk) };
+            let _ = console.write_str(s);
+        }
+    }
+
+    #[cfg(not(feature = "rzcobs"))]
+    {
         let _ = write!(console, "[DEFMT-A:");
         for byte in bytes {
             let _ = write!(console, "{:02X}", byte);
@@ -140,3 +264,71 @@ unsafe impl defmt::Logger for LoggerA {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commits_a_multi_push_frame_as_one_chunk() {
+        let ring: RingBuffer<64> = RingBuffer::new();
+        let mut frame = FrameBuf::new();
+
+        // Mirrors what `start_frame`/`write`/`end_frame` each do to the
+        // in-flight frame via separate `write_bytes` calls.
+        frame.push(&[0xAA]);
+        frame.push(&[0xBB, 0xCC]);
+        frame.push(&[0xDD]);
+        frame.commit(&ring);
+
+        let mut out = Vec::new();
+        ring.drain(|bytes| {
+            out.extend_from_slice(bytes);
+            bytes.len()
+        });
+        assert_eq!(out, vec![0xAA, 0xBB, 0xCC, 0xDD]);
+        assert_eq!(ring.take_dropped(), 0);
+    }
+
+    #[test]
+    fn drops_the_whole_frame_on_overflow() {
+        let ring: RingBuffer<64> = RingBuffer::new();
+        let mut frame = FrameBuf::new();
+
+        frame.push(&[0; MAX_FRAME_LEN]);
+        frame.push(&[0xFF]); // one byte over capacity
+        assert!(frame.overflowed);
+
+        frame.commit(&ring);
+        assert_eq!(ring.take_dropped(), 1);
+
+        let mut out = Vec::new();
+        ring.drain(|bytes| {
+            out.extend_from_slice(bytes);
+            bytes.len()
+        });
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn commit_resets_state_for_the_next_frame() {
+        let ring: RingBuffer<64> = RingBuffer::new();
+        let mut frame = FrameBuf::new();
+
+        frame.push(&[1, 2, 3]);
+        frame.commit(&ring);
+        frame.push(&[4, 5]);
+        frame.commit(&ring);
+
+        let mut out = Vec::new();
+        ring.drain(|bytes| {
+            out.extend_from_slice(bytes);
+            bytes.len()
+        });
+        ring.drain(|bytes| {
+            out.extend_from_slice(bytes);
+            bytes.len()
+        });
+        assert_eq!(out, vec![1, 2, 3, 4, 5]);
+    }
+}
@@ -0,0 +1,187 @@
+//! A small lock-free single-producer/single-consumer ring buffer.
+//!
+//! This fills the same role as `bbqueue`'s grant API but is hand-rolled to
+//! avoid pulling in an extra dependency for something this small: the
+//! producer (the defmt critical section, which pushes each completed frame
+//! when the logger is released) pushes raw encoded bytes in, and the consumer (the drain task
+//! in `task.rs`) pulls contiguous batches out to hand to the Tock console.
+//!
+//! Capacity is fixed at `N` bytes, one of which is always kept empty so the
+//! full and empty states can be told apart using only the `head`/`tail`
+//! cursors.
+
+use core::{
+    cell::UnsafeCell,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+pub struct RingBuffer<const N: usize> {
+    buf: UnsafeCell<[u8; N]>,
+    /// Free-running count of bytes the producer has written; `head % N` is
+    /// the slot of the next byte it will write.
+    head: AtomicUsize,
+    /// Free-running count of bytes the consumer has read; `tail % N` is the
+    /// slot of the next byte it will read.
+    tail: AtomicUsize,
+    /// Number of chunks dropped, either because the buffer was full when
+    /// [`push`] was called or because they were counted via `note_dropped`.
+    dropped: AtomicUsize,
+}
+
+// safety: `RingBuffer` is only ever accessed through `&self`, and the
+// single-producer/single-consumer contract documented on `push`/`drain`
+// keeps the two sides from touching overlapping regions of `buf`.
+unsafe impl<const N: usize> Sync for RingBuffer<N> {}
+
+impl<const N: usize> RingBuffer<N> {
+    pub const fn new() -> Self {
+        RingBuffer {
+            buf: UnsafeCell::new([0; N]),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            dropped: AtomicUsize::new(0),
+        }
+    }
+
+    /// Producer side: push `bytes` into the buffer.
+    ///
+    /// Must only be called by one producer at a time (here: the defmt
+    /// critical section). If there isn't room for the whole chunk, the
+    /// chunk is dropped in its entirety and counted rather than partially
+    /// written, so a reader never sees a truncated frame.
+    pub fn push(&self, bytes: &[u8]) {
+        let tail = self.tail.load(Ordering::Acquire);
+        let head = self.head.load(Ordering::Relaxed);
+        let used = head.wrapping_sub(tail);
+        let free = N - 1 - used;
+        if bytes.len() > free {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+
+        // safety: single producer, and the consumer only ever reads the
+        // `[tail, head)` region, which this loop writes past the end of.
+        let buf = unsafe { &mut *self.buf.get() };
+        for (i, &byte) in bytes.iter().enumerate() {
+            buf[(head + i) % N] = byte;
+        }
+        self.head.store(head + bytes.len(), Ordering::Release);
+    }
+
+    /// Consumer side: hands `f` the largest contiguous run of unread bytes
+    /// currently available and releases the number of bytes `f` reports
+    /// having consumed. No-op if the buffer is empty.
+    pub fn drain(&self, f: impl FnOnce(&[u8]) -> usize) {
+        let head = self.head.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Relaxed);
+        let available = head.wrapping_sub(tail);
+        if available == 0 {
+            return;
+        }
+
+        let start = tail % N;
+        let contiguous = core::cmp::min(available, N - start);
+        // safety: single consumer, and the producer never writes into the
+        // `[tail, head)` region this slice is drawn from.
+        let buf = unsafe { &*self.buf.get() };
+        let consumed = f(&buf[start..start + contiguous]);
+        self.tail.store(tail + consumed, Ordering::Release);
+    }
+
+    /// Number of chunks dropped (by a full buffer or via `note_dropped`),
+    /// reset to 0.
+    pub fn take_dropped(&self) -> usize {
+        self.dropped.swap(0, Ordering::Relaxed)
+    }
+
+    /// Count a chunk as dropped without attempting to push it.
+    ///
+    /// For callers that assemble a chunk elsewhere (e.g. a whole-frame
+    /// scratch buffer) and decide it can never fit, so there's no point
+    /// calling [`push`](Self::push) just to have it reject the chunk.
+    pub fn note_dropped(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drain_all<const N: usize>(ring: &RingBuffer<N>) -> Vec<u8> {
+        let mut out = Vec::new();
+        // A single `drain` call only yields one contiguous grant, so loop
+        // until there's nothing left to make progress on.
+        loop {
+            let before = out.len();
+            ring.drain(|bytes| {
+                out.extend_from_slice(bytes);
+                bytes.len()
+            });
+            if out.len() == before {
+                break;
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn push_and_drain_round_trip() {
+        let ring: RingBuffer<8> = RingBuffer::new();
+        ring.push(b"hello");
+
+        assert_eq!(drain_all(&ring), b"hello");
+        assert_eq!(ring.take_dropped(), 0);
+    }
+
+    #[test]
+    fn fills_to_capacity_without_dropping() {
+        let ring: RingBuffer<8> = RingBuffer::new();
+        // One byte of an 8-byte buffer is always kept empty, so 7 is the
+        // most a single push can hold.
+        ring.push(&[1, 2, 3, 4, 5, 6, 7]);
+
+        assert_eq!(ring.take_dropped(), 0);
+        assert_eq!(drain_all(&ring), vec![1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn push_when_full_drops_the_whole_chunk_and_counts_it() {
+        let ring: RingBuffer<8> = RingBuffer::new();
+        ring.push(&[1, 2, 3, 4, 5, 6, 7]);
+        // No room left at all; this chunk must be dropped in full rather
+        // than partially written.
+        ring.push(&[8, 9]);
+
+        assert_eq!(ring.take_dropped(), 1);
+        // take_dropped() resets the counter.
+        assert_eq!(ring.take_dropped(), 0);
+        assert_eq!(drain_all(&ring), vec![1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn drain_crosses_the_wraparound_boundary() {
+        let ring: RingBuffer<8> = RingBuffer::new();
+        ring.push(&[1, 2, 3, 4, 5]);
+        assert_eq!(drain_all(&ring), vec![1, 2, 3, 4, 5]); // head == tail == 5
+
+        // This push wraps past the end of the 8-byte backing array, so
+        // draining it back out takes two grants: [5..8) then [0..2).
+        ring.push(&[10, 11, 12, 13, 14]);
+
+        let mut grants = 0;
+        let mut out = Vec::new();
+        while out.len() < 5 {
+            let before = out.len();
+            ring.drain(|bytes| {
+                out.extend_from_slice(bytes);
+                bytes.len()
+            });
+            assert!(out.len() > before, "drain made no progress");
+            grants += 1;
+        }
+
+        assert_eq!(out, vec![10, 11, 12, 13, 14]);
+        assert!(grants > 1, "expected the wrapped write to need >1 grant");
+    }
+}
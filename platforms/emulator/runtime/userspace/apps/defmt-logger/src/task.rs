@@ -1,8 +1,30 @@
-//! Embassy task for defmt-logger example
+//! Embassy tasks for defmt-logger
 
 use core::fmt::Write;
 use libtock_console::Console;
 
+/// How often the ring buffer is checked for data to drain, in milliseconds.
+///
+/// This is a latency/overhead tradeoff: shorter intervals make log output
+/// appear sooner but drain smaller (less efficient) batches, longer
+/// intervals do the opposite.
+const DRAIN_INTERVAL_MS: u64 = 10;
+
+/// Drains the defmt ring buffer to the Tock console in large batches.
+///
+/// Must be spawned by any application that uses `defmt-logger`'s macros,
+/// otherwise encoded frames accumulate in the ring buffer and are never
+/// written out. Moving this work out of the defmt critical section (which
+/// only does an O(memcpy) push into the ring, see `logger_a`/`logger_b`)
+/// keeps logging calls cheap even when the console itself is slow.
+#[embassy_executor::task]
+pub async fn drain_task() {
+    loop {
+        crate::drain();
+        embassy_time::Timer::after_millis(DRAIN_INTERVAL_MS).await;
+    }
+}
+
 /// Example embassy task that demonstrates logging
 #[embassy_executor::task]
 pub async fn logger_demo_task() {
@@ -94,6 +94,15 @@ pub(crate) async fn async_main<S: Syscalls>() {
         .spawn(defmt_logger::task::logger_demo_task())
         .unwrap();
 
+    // Spawn the task that drains encoded defmt frames to the console. Without
+    // this, frames queue up in the logger's ring buffer and are never
+    // written out.
+    EXECUTOR
+        .get()
+        .spawner()
+        .spawn(defmt_logger::task::drain_task())
+        .unwrap();
+
     writeln!(console_writer, "Tasks spawned successfully").unwrap();
 
     // Main executor loop
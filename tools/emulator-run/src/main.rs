@@ -5,13 +5,14 @@
 //! as they are emitted by the running application.
 
 use std::{
+    collections::HashMap,
     env, fs,
-    io::{BufRead, BufReader},
+    io::{BufRead, BufReader, Read},
     process::{self, Command, Stdio},
 };
 
 use anyhow::{anyhow, bail};
-use defmt_decoder::{DecodeError, StreamDecoder, Table};
+use defmt_decoder::{DecodeError, Level, StreamDecoder, Table};
 use process::Child;
 
 fn main() -> Result<(), anyhow::Error> {
@@ -26,11 +27,33 @@ fn notmain() -> Result<Option<i32>, anyhow::Error> {
     let args = env::args().skip(1 /* program name */).collect::<Vec<_>>();
 
     if args.is_empty() {
-        bail!("Usage: emulator-run <path-to-hello-world-app-elf>\n\nThis tool will:\n1. Parse the .defmt section from the ELF\n2. Run 'cargo xtask runtime'\n3. Decode and display defmt logs from the emulator output");
+        bail!("Usage: emulator-run [--level <trace|debug|info|warn|error>] [--framing <hex|rzcobs>] <path-to-hello-world-app-elf>\n\nThis tool will:\n1. Parse the .defmt section from the ELF\n2. Run 'cargo xtask runtime'\n3. Decode and display defmt logs from the emulator output\n\n--level filters out frames below the given defmt level (default: show everything).\n--framing selects how frames are transported on the console (default: hex, the ASCII-hex `[DEFMT-<tag>:..]` lines used for debugging; rzcobs expects the self-synchronizing binary framing from the `rzcobs` defmt-logger feature).");
     }
 
-    let elf_path = &args[0];
-    let bytes = fs::read(elf_path)?;
+    let mut elf_path = None;
+    let mut min_level = None;
+    let mut framing = Framing::Hex;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--level" {
+            let value = args
+                .next()
+                .ok_or_else(|| anyhow!("--level requires a value"))?;
+            min_level = Some(parse_level(&value)?);
+        } else if arg == "--framing" {
+            let value = args
+                .next()
+                .ok_or_else(|| anyhow!("--framing requires a value"))?;
+            framing = parse_framing(&value)?;
+        } else if elf_path.is_none() {
+            elf_path = Some(arg);
+        } else {
+            bail!("unexpected argument: {}", arg);
+        }
+    }
+    let elf_path = elf_path.ok_or_else(|| anyhow!("missing path to ELF file"))?;
+
+    let bytes = fs::read(&elf_path)?;
 
     let table = if env::var_os("EMULATOR_RUN_IGNORE_VERSION").is_some() {
         Table::parse_ignore_version(&bytes)
@@ -58,40 +81,59 @@ fn notmain() -> Result<Option<i32>, anyhow::Error> {
         .ok_or_else(|| anyhow!("failed to acquire child's stdout handle"))?;
 
     let mut reader = BufReader::new(stdout);
-    let mut decoder = table.new_stream_decoder();
 
     eprintln!("Emulator started, watching for defmt data...\n");
 
+    let exit_code = match framing {
+        Framing::Hex => pump_hex_frames(&mut reader, &mut child, &table, min_level)?,
+        Framing::Rzcobs => pump_rzcobs_frames(&mut reader, &mut child, &table, min_level)?,
+    };
+
+    Ok(exit_code)
+}
+
+/// Read newline-delimited `[DEFMT-<tag>:<hex>]` frames from `reader` until
+/// the emulator exits, decoding and printing them as they arrive.
+///
+/// Each logger channel (e.g. logger_a's "A", logger_b's "B") gets its own
+/// `StreamDecoder` so interleaved frames from different channels don't
+/// corrupt each other's in-progress frame state.
+fn pump_hex_frames(
+    reader: &mut impl BufRead,
+    child: &mut KillOnDrop,
+    table: &Table,
+    min_level: Option<Level>,
+) -> Result<Option<i32>, anyhow::Error> {
+    let mut decoders: HashMap<String, Box<dyn StreamDecoder>> = HashMap::new();
     let mut line = String::new();
-    let exit_code = loop {
+
+    loop {
         line.clear();
         match reader.read_line(&mut line) {
             Ok(0) => {
                 // EOF
                 if let Some(status) = child.0.try_wait()? {
-                    break status.code();
+                    return Ok(status.code());
                 }
             }
             Ok(_) => {
-                // Check if this line contains defmt data
-                if line.starts_with("[DEFMT:") && line.contains(']') {
-                    // Extract hex bytes between [DEFMT: and ]
-                    if let Some(hex_str) = line.strip_prefix("[DEFMT:").and_then(|s| s.strip_suffix("]\n").or_else(|| s.strip_suffix("]"))) {
-                        // Convert hex string to bytes
-                        let mut bytes = Vec::new();
-                        for i in (0..hex_str.len()).step_by(2) {
-                            if i + 1 < hex_str.len() {
-                                if let Ok(byte) = u8::from_str_radix(&hex_str[i..i+2], 16) {
-                                    bytes.push(byte);
-                                }
+                // Check if this line contains a defmt frame, e.g. "[DEFMT-A:...]"
+                if let Some((tag, hex_str)) = parse_defmt_line(&line) {
+                    let mut bytes = Vec::new();
+                    for i in (0..hex_str.len()).step_by(2) {
+                        if i + 1 < hex_str.len() {
+                            if let Ok(byte) = u8::from_str_radix(&hex_str[i..i + 2], 16) {
+                                bytes.push(byte);
                             }
                         }
+                    }
 
-                        // Feed bytes to decoder
-                        if !bytes.is_empty() {
-                            decoder.received(&bytes);
-                            let _ = decode(&mut *decoder);
-                        }
+                    if !bytes.is_empty() {
+                        let decoder = decoders
+                            .entry(tag.to_string())
+                            .or_insert_with(|| table.new_stream_decoder());
+                        decoder.received(&bytes);
+                        let _ = decode(tag, &mut **decoder, min_level);
                     }
                 } else {
                     // Normal output line, just print it
@@ -100,23 +142,94 @@ fn notmain() -> Result<Option<i32>, anyhow::Error> {
             }
             Err(e) => {
                 eprintln!("Error reading emulator output: {}", e);
-                break None;
+                return Ok(None);
             }
         }
 
         if let Some(status) = child.0.try_wait()? {
-            break status.code();
+            return Ok(status.code());
         }
-    };
+    }
+}
 
-    Ok(exit_code)
+/// Read rzCOBS-framed frames from `reader` until the emulator exits,
+/// decoding and printing them as they arrive.
+///
+/// Each frame is a run of bytes that can never contain `0x00` followed by a
+/// `0x00` sentinel, so frame boundaries are self-synchronizing: bytes are
+/// accumulated until a sentinel is seen, and the packet in front of it is
+/// rzCOBS-decoded. This console stream isn't exclusively defmt frames
+/// though - plain `writeln!` output from the app shares it too - so a
+/// packet that fails to decode is assumed to be that plain output (or a
+/// corrupted frame) and is passed through as text rather than silently
+/// dropped; either way the packet is cleared, which is what resyncs
+/// decoding on the next sentinel. Unlike the hex framing, this mode uses a
+/// single `StreamDecoder` since the transport carries no channel tag.
+fn pump_rzcobs_frames(
+    reader: &mut impl Read,
+    child: &mut KillOnDrop,
+    table: &Table,
+    min_level: Option<Level>,
+) -> Result<Option<i32>, anyhow::Error> {
+    let mut decoder = table.new_stream_decoder();
+    let mut packet = Vec::new();
+    let mut byte = [0u8; 1];
+
+    loop {
+        match reader.read(&mut byte) {
+            Ok(0) => {
+                // EOF. Whatever's left in `packet` was never terminated by a
+                // sentinel, so it can't be a complete frame - surface it as
+                // text rather than dropping the app's last bit of output.
+                if !packet.is_empty() {
+                    print!("{}", String::from_utf8_lossy(&packet));
+                    packet.clear();
+                }
+                if let Some(status) = child.0.try_wait()? {
+                    return Ok(status.code());
+                }
+            }
+            Ok(_) => {
+                if byte[0] == 0x00 {
+                    match rzcobs::decode(&packet) {
+                        Ok(decoded) => {
+                            decoder.received(&decoded);
+                            let _ = decode("rzcobs", &mut *decoder, min_level);
+                        }
+                        Err(_) => print!("{}", String::from_utf8_lossy(&packet)),
+                    }
+                    packet.clear();
+                } else {
+                    packet.push(byte[0]);
+                }
+            }
+            Err(e) => {
+                eprintln!("Error reading emulator output: {}", e);
+                return Ok(None);
+            }
+        }
+
+        if let Some(status) = child.0.try_wait()? {
+            return Ok(status.code());
+        }
+    }
 }
 
-fn decode(decoder: &mut dyn StreamDecoder) -> Result<(), DecodeError> {
+fn decode(
+    tag: &str,
+    decoder: &mut dyn StreamDecoder,
+    min_level: Option<Level>,
+) -> Result<(), DecodeError> {
     loop {
         match decoder.decode() {
             Ok(frame) => {
-                eprintln!("[defmt] {}", frame.display(true));
+                let visible = match (min_level, frame.level()) {
+                    (Some(min), Some(level)) => level >= min,
+                    _ => true,
+                };
+                if visible {
+                    eprintln!("[defmt:{}] {}", tag, frame.display(true));
+                }
             }
             Err(DecodeError::UnexpectedEof) => return Ok(()),
             Err(DecodeError::Malformed) => {
@@ -128,6 +241,56 @@ fn decode(decoder: &mut dyn StreamDecoder) -> Result<(), DecodeError> {
     }
 }
 
+/// Parse a line of the form `[DEFMT-<tag>:<hex>]`, returning the channel tag
+/// and the hex payload. Lines that don't match this shape (plain app output,
+/// `writeln!` noise, etc.) return `None`.
+fn parse_defmt_line(line: &str) -> Option<(&str, &str)> {
+    let rest = line.strip_prefix("[DEFMT-")?;
+    let colon = rest.find(':')?;
+    let tag = &rest[..colon];
+    let after_tag = &rest[colon + 1..];
+    let hex_str = after_tag
+        .strip_suffix("]\n")
+        .or_else(|| after_tag.strip_suffix(']'))?;
+    Some((tag, hex_str))
+}
+
+/// How defmt frames are transported over the console.
+#[derive(Clone, Copy)]
+enum Framing {
+    /// Newline-delimited ASCII-hex `[DEFMT-<tag>:<hex>]` lines. Readable in a
+    /// plain terminal, which is handy for debugging, but doubles the byte
+    /// count on the wire and can't recover if a log spans a buffer boundary.
+    Hex,
+    /// Self-synchronizing binary framing: each frame is emitted as rzCOBS-
+    /// encoded bytes followed by a `0x00` sentinel.
+    Rzcobs,
+}
+
+/// Parse a `--framing` argument into a [`Framing`].
+fn parse_framing(value: &str) -> Result<Framing, anyhow::Error> {
+    Ok(match value.to_ascii_lowercase().as_str() {
+        "hex" => Framing::Hex,
+        "rzcobs" => Framing::Rzcobs,
+        other => bail!("invalid --framing '{}', expected 'hex' or 'rzcobs'", other),
+    })
+}
+
+/// Parse a `--level` argument into a defmt [`Level`].
+fn parse_level(value: &str) -> Result<Level, anyhow::Error> {
+    Ok(match value.to_ascii_lowercase().as_str() {
+        "trace" => Level::Trace,
+        "debug" => Level::Debug,
+        "info" => Level::Info,
+        "warn" | "warning" => Level::Warn,
+        "error" => Level::Error,
+        other => bail!(
+            "invalid --level '{}', expected one of: trace, debug, info, warn, error",
+            other
+        ),
+    })
+}
+
 struct KillOnDrop(Child);
 
 impl Drop for KillOnDrop {